@@ -1,10 +1,74 @@
 use polars::prelude::*;
 use std::path::Path;
-use aws_sdk_s3::{primitives::ByteStream, Client};
-use std::io::Cursor;
+use std::sync::Arc;
+use std::time::Duration;
 use log::{info, warn};
 use std::fmt;
-use aws_sdk_s3::error::SdkError;
+use crate::storage::{ObjectStore, StorageError};
+
+/// Files larger than this use a multipart upload instead of a single
+/// `put_object`/`put` call, so a multi-GB Parquet output doesn't need to
+/// sit fully buffered behind one request.
+const MULTIPART_THRESHOLD_BYTES: usize = 32 * 1024 * 1024;
+
+/// Size of each part fed to a multipart upload once the threshold above
+/// is crossed (within S3's 5 MiB minimum for non-final parts).
+const MULTIPART_PART_SIZE_BYTES: usize = 8 * 1024 * 1024;
+
+/// Whether an output of `file_size` bytes should be uploaded via multipart
+/// rather than a single `put`.
+fn should_use_multipart(file_size: u64) -> bool {
+    file_size as usize > MULTIPART_THRESHOLD_BYTES
+}
+
+/// Serialization format for the converted output. Selects both the
+/// Polars writer used and the uploaded key's file extension, so callers
+/// that can't read Parquet can consume the same pipeline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Parquet,
+    ArrowIpc,
+    Avro,
+    NdJson,
+    Csv,
+}
+
+impl OutputFormat {
+    /// File extension (without the leading dot) used for the uploaded key.
+    fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Parquet => "parquet",
+            OutputFormat::ArrowIpc => "arrow",
+            OutputFormat::Avro => "avro",
+            OutputFormat::NdJson => "ndjson",
+            OutputFormat::Csv => "csv",
+        }
+    }
+
+    /// Whether Polars exposes a streaming sink for this format. Avro and
+    /// NDJSON only have eager writers today.
+    fn supports_streaming_sink(&self) -> bool {
+        matches!(self, OutputFormat::Parquet | OutputFormat::ArrowIpc | OutputFormat::Csv)
+    }
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "parquet" => Ok(OutputFormat::Parquet),
+            "arrow" | "ipc" | "feather" => Ok(OutputFormat::ArrowIpc),
+            "avro" => Ok(OutputFormat::Avro),
+            "ndjson" | "jsonl" => Ok(OutputFormat::NdJson),
+            "csv" => Ok(OutputFormat::Csv),
+            other => Err(format!(
+                "unsupported OUTPUT_FORMAT '{}', expected one of: parquet, arrow, avro, ndjson, csv",
+                other
+            )),
+        }
+    }
+}
 
 /// Custom error type for CSV processing operations that implements Send + Sync
 #[derive(Debug)]
@@ -13,7 +77,7 @@ pub enum CsvProcessingError {
     IoError(std::io::Error),
     InvalidPath(String),
     EmptyDataFrame(String),
-    ParquetWriteError(String),
+    OutputWriteError(String),
 }
 
 impl fmt::Display for CsvProcessingError {
@@ -23,7 +87,7 @@ impl fmt::Display for CsvProcessingError {
             CsvProcessingError::IoError(e) => write!(f, "I/O operation failed: {}", e),
             CsvProcessingError::InvalidPath(path) => write!(f, "Invalid file path: {}", path),
             CsvProcessingError::EmptyDataFrame(msg) => write!(f, "Empty DataFrame: {}", msg),
-            CsvProcessingError::ParquetWriteError(msg) => write!(f, "Parquet write failed: {}", msg),
+            CsvProcessingError::OutputWriteError(msg) => write!(f, "Output write failed: {}", msg),
         }
     }
 }
@@ -54,12 +118,16 @@ impl From<std::io::Error> for CsvProcessingError {
     }
 }
 
-/// Synchronous function to process CSV file and return parquet bytes with row count
+/// Synchronous function to process a CSV file into a serialized output file
+/// on disk, returning that file (plus its size and row count) rather than
+/// its bytes, so the caller can decide single-shot vs. multipart upload
+/// without first reading the whole output into memory.
 /// This function handles all CPU-intensive Polars operations in a thread-safe manner
-fn process_csv_to_parquet_bytes(
+fn process_csv_to_output_file(
     csv_path: &Path,
-    allowed_platforms: &[&str]
-) -> Result<Option<(Vec<u8>, u64)>, CsvProcessingError> {
+    allowed_platforms: &[&str],
+    format: OutputFormat,
+) -> Result<Option<(tempfile::NamedTempFile, u64, u64)>, CsvProcessingError> {
     // Validate file path
     if !csv_path.exists() {
         return Err(CsvProcessingError::InvalidPath(
@@ -67,41 +135,118 @@ fn process_csv_to_parquet_bytes(
         ));
     }
 
-    // Read CSV and apply filters - all synchronous operations
-    let filtered_df = LazyCsvReader::new(csv_path)
+    // Build the filtered query lazily - nothing is read or materialized yet
+    let filtered_lazy = LazyCsvReader::new(csv_path)
         .with_has_header(true)
         .finish()
         .map_err(|e| CsvProcessingError::PolarsError(e))?
         .filter(
             col("platform_name").is_in(lit(Series::new(
-                PlSmallStr::from_static("platforms"), 
+                PlSmallStr::from_static("platforms"),
                 allowed_platforms
             )), false)
-        )
-        .collect()
-        .map_err(|e| CsvProcessingError::PolarsError(e))?;
+        );
+
+    let row_count = count_rows(filtered_lazy.clone())?;
 
     // Handle empty DataFrame case by returning None
-    if filtered_df.height() == 0 {
+    if row_count == 0 {
         let msg = format!("No rows after filtering for file: {}", csv_path.display());
         warn!("{}", msg);
         return Ok(None);
     }
 
-    // Get row count before writing to buffer
-    let row_count = filtered_df.height() as u64;
+    let temp_output = tempfile::NamedTempFile::new()?;
 
-    // Write parquet to memory buffer - synchronous
-    let mut buffer = Vec::new();
-    let mut cursor = Cursor::new(&mut buffer);
-    
-    ParquetWriter::new(&mut cursor)
-        .finish(&mut filtered_df.clone())
-        .map_err(|e| CsvProcessingError::ParquetWriteError(
-            format!("Failed to write parquet for {}: {}", csv_path.display(), e)
-        ))?;
+    // Prefer the streaming sink where one exists for the format: it
+    // writes rows to disk in bounded batches instead of materializing the
+    // whole filtered DataFrame plus a second in-memory output buffer. Not
+    // every query plan is supported by the streaming engine, so fall back
+    // to the eager path (collect, then write) when the sink rejects it.
+    let sink_result = if format.supports_streaming_sink() {
+        Some(sink_streaming(filtered_lazy.clone(), temp_output.path(), format))
+    } else {
+        None
+    };
 
-    Ok(Some((buffer, row_count)))
+    match sink_result {
+        Some(Ok(())) => {}
+        Some(Err(e)) => {
+            warn!(
+                "Streaming sink rejected the query plan for {} ({:?}: {}), falling back to eager collect+write",
+                csv_path.display(), format, e
+            );
+            write_eager(filtered_lazy, temp_output.path(), format)?;
+        }
+        None => write_eager(filtered_lazy, temp_output.path(), format)?,
+    }
+
+    let file_size = std::fs::metadata(temp_output.path())
+        .map_err(CsvProcessingError::IoError)?
+        .len();
+
+    Ok(Some((temp_output, file_size, row_count)))
+}
+
+/// Count the rows `lazy_frame` would produce without materializing them.
+fn count_rows(lazy_frame: LazyFrame) -> Result<u64, CsvProcessingError> {
+    let count_df = lazy_frame
+        .select([len()])
+        .collect()
+        .map_err(CsvProcessingError::PolarsError)?;
+
+    // `len()` is backed by Polars' IDX_DTYPE, which is `u32` by default but
+    // `u64` under the `bigidx` feature - cast explicitly instead of
+    // assuming `u32` so this doesn't break under that feature (or a future
+    // default change).
+    let count = count_df
+        .column("len")
+        .map_err(CsvProcessingError::PolarsError)?
+        .cast(&DataType::UInt64)
+        .map_err(CsvProcessingError::PolarsError)?
+        .u64()
+        .map_err(CsvProcessingError::PolarsError)?
+        .get(0)
+        .unwrap_or(0);
+
+    Ok(count)
+}
+
+/// Write `lazy_frame` to `path` using Polars' streaming engine for
+/// `format`, writing rows in bounded batches rather than collecting the
+/// whole frame first. Only called for formats where
+/// [`OutputFormat::supports_streaming_sink`] is true.
+fn sink_streaming(lazy_frame: LazyFrame, path: &Path, format: OutputFormat) -> PolarsResult<()> {
+    match format {
+        OutputFormat::Parquet => lazy_frame.sink_parquet(path.to_path_buf(), ParquetWriteOptions::default()),
+        OutputFormat::ArrowIpc => lazy_frame.sink_ipc(path.to_path_buf(), IpcWriterOptions::default()),
+        OutputFormat::Csv => lazy_frame.sink_csv(path.to_path_buf(), CsvWriterOptions::default()),
+        OutputFormat::Avro | OutputFormat::NdJson => {
+            unreachable!("{:?} has no streaming sink", format)
+        }
+    }
+}
+
+/// Eagerly collect and write `lazy_frame`, used when the streaming engine
+/// can't execute the query plan (it doesn't support every operator), or
+/// for formats without a streaming sink at all.
+fn write_eager(lazy_frame: LazyFrame, path: &Path, format: OutputFormat) -> Result<(), CsvProcessingError> {
+    let mut df = lazy_frame.collect().map_err(CsvProcessingError::PolarsError)?;
+    let file = std::fs::File::create(path).map_err(CsvProcessingError::IoError)?;
+
+    let result = match format {
+        OutputFormat::Parquet => ParquetWriter::new(file).finish(&mut df).map(|_| ()),
+        OutputFormat::ArrowIpc => IpcWriter::new(file).finish(&mut df),
+        OutputFormat::Avro => AvroWriter::new(file).finish(&mut df),
+        OutputFormat::NdJson => JsonWriter::new(file)
+            .with_json_format(JsonFormat::JsonLines)
+            .finish(&mut df),
+        OutputFormat::Csv => CsvWriter::new(file).finish(&mut df),
+    };
+
+    result.map_err(|e| CsvProcessingError::OutputWriteError(
+        format!("Failed to write {:?} for {}: {}", format, path.display(), e)
+    ))
 }
 
 /// Custom error type for the async orchestrator function
@@ -109,8 +254,9 @@ fn process_csv_to_parquet_bytes(
 pub enum UploadError {
     CsvProcessing(CsvProcessingError),
     TaskJoin(String),
-    S3Upload(String),
+    Storage(StorageError),
     InvalidFilePath(String),
+    Io(std::io::Error),
 }
 
 impl fmt::Display for UploadError {
@@ -118,8 +264,9 @@ impl fmt::Display for UploadError {
         match self {
             UploadError::CsvProcessing(e) => write!(f, "CSV processing error: {}", e),
             UploadError::TaskJoin(msg) => write!(f, "Task join error: {}", msg),
-            UploadError::S3Upload(msg) => write!(f, "S3 upload error: {}", msg),
+            UploadError::Storage(e) => write!(f, "Storage error: {}", e),
             UploadError::InvalidFilePath(msg) => write!(f, "Invalid file path: {}", msg),
+            UploadError::Io(e) => write!(f, "I/O error: {}", e),
         }
     }
 }
@@ -128,6 +275,8 @@ impl std::error::Error for UploadError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
             UploadError::CsvProcessing(e) => Some(e),
+            UploadError::Storage(e) => Some(e),
+            UploadError::Io(e) => Some(e),
             _ => None,
         }
     }
@@ -139,17 +288,38 @@ impl From<CsvProcessingError> for UploadError {
     }
 }
 
+impl From<StorageError> for UploadError {
+    fn from(error: StorageError) -> Self {
+        UploadError::Storage(error)
+    }
+}
+
+impl From<std::io::Error> for UploadError {
+    fn from(error: std::io::Error) -> Self {
+        UploadError::Io(error)
+    }
+}
+
 // Ensure Send + Sync traits are implemented for UploadError
 unsafe impl Send for UploadError {}
 unsafe impl Sync for UploadError {}
 
+/// Result of a successful conversion and upload.
+pub struct UploadOutcome {
+    pub row_count: u64,
+    /// Time-limited GET URL for the uploaded object, if the destination
+    /// backend supports presigning (see [`ObjectStore::presign_get`]).
+    pub download_url: Option<String>,
+}
+
 pub async fn convert_filter_and_upload_direct(
     csv_path: &Path,
-    s3_client: &Client,
-    bucket: &str,
+    store: &Arc<dyn ObjectStore>,
     prefix: &str,
-) -> Result<Option<u64>, UploadError> {
-    
+    format: OutputFormat,
+    presigned_url_expiry: Duration,
+) -> Result<Option<UploadOutcome>, UploadError> {
+
     // Create the allowed platforms list
     let allowed_platforms = &[
         "Facebook",
@@ -171,19 +341,19 @@ pub async fn convert_filter_and_upload_direct(
     
     let join_handle = tokio::task::spawn_blocking(move || {
         let allowed_platforms_refs: Vec<&str> = allowed_platforms_owned.iter().map(|s| s.as_str()).collect();
-        process_csv_to_parquet_bytes(&csv_path_owned, &allowed_platforms_refs)
+        process_csv_to_output_file(&csv_path_owned, &allowed_platforms_refs, format)
     });
-    
+
     // Handle the JoinHandle result properly and propagate errors with context
     let result_option = join_handle.await
         .map_err(|join_err| UploadError::TaskJoin(
-            format!("Failed to join CSV processing task for {}: {}", 
+            format!("Failed to join CSV processing task for {}: {}",
                    csv_path.display(), join_err)
         ))?
         .map_err(|csv_err| UploadError::CsvProcessing(csv_err))?;
-    
-    let (buffer, row_count) = match result_option {
-        Some((buf, count)) => (buf, count),
+
+    let (temp_output, file_size, row_count) = match result_option {
+        Some((file, size, count)) => (file, size, count),
         None => return Ok(None), // No data after filtering
     };
 
@@ -195,71 +365,123 @@ pub async fn convert_filter_and_upload_direct(
         ))?
         .to_string_lossy();
     
-    let s3_key = format!("{}{}.parquet", prefix, file_name);
-    
-    // Log upload attempt for debugging
-    info!("Attempting to upload {} bytes to s3://{}/{}", 
-          buffer.len(), bucket, s3_key);
-    
-    // Log the current AWS region configuration for debugging
-    let current_region = std::env::var("S3_REGION").unwrap_or_else(|_| "not set".to_string());
-    info!("Using AWS region: {}", current_region);
-    
-    // Log AWS credential configuration status (without exposing actual values)
-    let aws_access_key_set = std::env::var("AWS_ACCESS_KEY_ID").is_ok();
-    let aws_secret_key_set = std::env::var("AWS_SECRET_ACCESS_KEY").is_ok();
-    let aws_profile_set = std::env::var("AWS_PROFILE").is_ok();
-    
-    info!("AWS credentials status - Access Key: {}, Secret Key: {}, Profile: {}", 
-          if aws_access_key_set { "SET" } else { "NOT SET" },
-          if aws_secret_key_set { "SET" } else { "NOT SET" },
-          if aws_profile_set { "SET" } else { "NOT SET" });
-    
-    s3_client
-        .put_object()
-        .bucket(bucket)
-        .key(&s3_key)
-        .body(ByteStream::from(buffer.clone()))
-        .send()
-        .await
-        .map_err(|s3_err| {
-            let detailed_error = match &s3_err {
-                SdkError::ServiceError(service_err) => {
-                    let status_code = service_err.raw().status().as_u16();
-                    let error_msg = format!("S3 Service Error: {} (HTTP {})", 
-                                          service_err.err(), status_code);
-                    
-                    // Add common troubleshooting hints based on status code
-                    match status_code {
-                        301 => format!("{} - REGION MISMATCH: Bucket '{}' exists in a different AWS region. Check S3_REGION environment variable", error_msg, bucket),
-                        403 => format!("{} - ACCESS DENIED: Check 1) AWS credentials are configured, 2) IAM user/role has s3:PutObject permission for bucket '{}', 3) Bucket policy allows your AWS account", error_msg, bucket),
-                        404 => format!("{} - Bucket '{}' may not exist or be accessible", error_msg, bucket),
-                        400 => format!("{} - Invalid request parameters", error_msg),
-                        500..=599 => format!("{} - AWS server error, retry may help", error_msg),
-                        _ => error_msg
-                    }
-                },
-                SdkError::TimeoutError(_) => "S3 request timed out - check network connectivity".to_string(),
-                SdkError::ResponseError(resp_err) => {
-                    format!("S3 Response Error: {:?} - check network connectivity", resp_err)
-                },
-                SdkError::DispatchFailure(dispatch_err) => {
-                    format!("S3 Dispatch Failure: {:?} - check AWS configuration", dispatch_err)
-                },
-                SdkError::ConstructionFailure(construct_err) => {
-                    format!("S3 Construction Failure: {:?} - check request parameters", construct_err)
-                },
-                _ => format!("Unknown S3 Error: {} - check AWS configuration and connectivity", s3_err)
-            };
-            
-            UploadError::S3Upload(
-                format!("Failed to upload {} to s3://{}/{}: {}", 
-                       file_name, bucket, s3_key, detailed_error)
-            )
-        })?;
-
-    info!("Processed and uploaded {} ({} rows) to s3://{}/{}", 
-          file_name, row_count, bucket, s3_key);
-    
-    Ok(Some(row_count))
+    let key = format!("{}{}.{}", prefix, file_name, format.extension());
+
+    info!("Attempting to upload {} bytes to {}", file_size, key);
+
+    if should_use_multipart(file_size) {
+        info!(
+            "{} exceeds multipart threshold ({} bytes), uploading in {} byte parts",
+            key, MULTIPART_THRESHOLD_BYTES, MULTIPART_PART_SIZE_BYTES
+        );
+        store.put_multipart(&key, temp_output.path(), MULTIPART_PART_SIZE_BYTES).await?;
+    } else {
+        let bytes = tokio::fs::read(temp_output.path()).await?;
+        store.put(&key, bytes).await?;
+    }
+
+    let download_url = store.presign_get(&key, presigned_url_expiry).await?;
+
+    info!("Processed and uploaded {} ({} rows) to {}", file_name, row_count, key);
+
+    Ok(Some(UploadOutcome { row_count, download_url }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn write_sample_csv() -> tempfile::NamedTempFile {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(file, "platform_name,data").unwrap();
+        writeln!(file, "Facebook,1").unwrap();
+        writeln!(file, "Facebook,2").unwrap();
+        writeln!(file, "Other,3").unwrap();
+        file.flush().unwrap();
+        file
+    }
+
+    #[test]
+    fn should_use_multipart_splits_at_the_threshold() {
+        assert!(!should_use_multipart(MULTIPART_THRESHOLD_BYTES as u64));
+        assert!(should_use_multipart(MULTIPART_THRESHOLD_BYTES as u64 + 1));
+    }
+
+    #[test]
+    fn count_rows_is_dtype_agnostic() {
+        let df = df!("a" => [1, 2, 3]).unwrap();
+        assert_eq!(count_rows(df.lazy()).unwrap(), 3);
+    }
+
+    #[test]
+    fn process_csv_uses_the_streaming_sink_for_csv_output() {
+        let csv = write_sample_csv();
+        let (temp_output, file_size, row_count) =
+            process_csv_to_output_file(csv.path(), &["Facebook"], OutputFormat::Csv)
+                .unwrap()
+                .unwrap();
+
+        assert_eq!(row_count, 2);
+        assert!(file_size > 0);
+        let contents = std::fs::read_to_string(temp_output.path()).unwrap();
+        assert!(contents.contains("Facebook"));
+        assert!(!contents.contains("Other"));
+    }
+
+    #[test]
+    fn process_csv_falls_back_to_the_eager_writer_for_avro() {
+        // Avro has no streaming sink (see `OutputFormat::supports_streaming_sink`),
+        // so this always takes the eager collect+write path.
+        let csv = write_sample_csv();
+        let (_temp_output, file_size, row_count) =
+            process_csv_to_output_file(csv.path(), &["Facebook"], OutputFormat::Avro)
+                .unwrap()
+                .unwrap();
+
+        assert_eq!(row_count, 2);
+        assert!(file_size > 0);
+    }
+
+    #[test]
+    fn output_format_parses_all_supported_aliases() {
+        assert_eq!("parquet".parse(), Ok(OutputFormat::Parquet));
+        assert_eq!("arrow".parse(), Ok(OutputFormat::ArrowIpc));
+        assert_eq!("ipc".parse(), Ok(OutputFormat::ArrowIpc));
+        assert_eq!("feather".parse(), Ok(OutputFormat::ArrowIpc));
+        assert_eq!("avro".parse(), Ok(OutputFormat::Avro));
+        assert_eq!("ndjson".parse(), Ok(OutputFormat::NdJson));
+        assert_eq!("jsonl".parse(), Ok(OutputFormat::NdJson));
+        assert_eq!("csv".parse(), Ok(OutputFormat::Csv));
+        assert_eq!("CSV".parse(), Ok(OutputFormat::Csv));
+        assert!("unsupported".parse::<OutputFormat>().is_err());
+    }
+
+    #[test]
+    fn every_output_format_produces_a_non_empty_file_with_the_right_row_count() {
+        for format in [
+            OutputFormat::Parquet,
+            OutputFormat::ArrowIpc,
+            OutputFormat::Avro,
+            OutputFormat::NdJson,
+            OutputFormat::Csv,
+        ] {
+            let csv = write_sample_csv();
+            let (_temp_output, file_size, row_count) =
+                process_csv_to_output_file(csv.path(), &["Facebook"], format)
+                    .unwrap()
+                    .unwrap_or_else(|| panic!("expected output for format {:?}", format));
+
+            assert_eq!(row_count, 2, "wrong row count for format {:?}", format);
+            assert!(file_size > 0, "empty output file for format {:?}", format);
+        }
+    }
+
+    #[test]
+    fn process_csv_returns_none_when_nothing_matches_allowed_platforms() {
+        let csv = write_sample_csv();
+        let result =
+            process_csv_to_output_file(csv.path(), &["Nobody"], OutputFormat::Csv).unwrap();
+        assert!(result.is_none());
+    }
 }
\ No newline at end of file
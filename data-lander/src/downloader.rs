@@ -2,26 +2,36 @@ use reqwest;
 use tempfile::NamedTempFile;
 use tokio::io::AsyncWriteExt;
 use std::time::Duration;
-use tracing::{info, error, instrument};
+use tracing::{info, warn, error, instrument};
+use futures_util::StreamExt;
+
+/// Maximum number of times a dropped connection is retried with a `Range`
+/// request before giving up.
+const MAX_RETRIES: u32 = 5;
+
+/// Backoff before the first retry; doubled on each subsequent attempt.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
 
 #[instrument(skip_all, fields(url = %url))]
 pub async fn download_zip_to_temp(
     url: &str,
 ) -> Result<NamedTempFile, Box<dyn std::error::Error>> {
     info!("Starting ZIP download");
-    
+
     // Puts a temp file in ephemeral storage on ECS Fargate
     // When temp_file goes out of scope, it will be deleted
     let temp_file = NamedTempFile::with_suffix(".zip")?;
-    let response = create_client().get(url).send().await?;
-    
+    let client = create_client();
+    let response = client.get(url).send().await?;
+
     validate_response(&response)?;
 
-    // Stream the response to a temporary file
-    stream_to_file(response, &temp_file).await?;
-    
+    // Stream the response to a temporary file, resuming from where we
+    // left off if the connection drops mid-download
+    stream_to_file(&client, url, response, &temp_file).await?;
+
     info!(
-        temp_path = ?temp_file.path(), 
+        temp_path = ?temp_file.path(),
         size_bytes = temp_file.as_file().metadata()?.len(),
         "Download completed"
     );
@@ -47,16 +57,94 @@ fn validate_response(
     Ok(())
 }
 
+/// Validate the status of a `Range`-resumed retry. A server that honors
+/// the `Range` header answers `206 Partial Content`; one that ignores it
+/// (a proxy/CDN stripping the header, say) answers a fresh `200` with the
+/// full body instead, which would get `write_all`'d straight after the
+/// bytes already on disk and silently corrupt the file. Treat anything
+/// other than `206` as a hard failure rather than appending blindly.
+fn validate_resume_status(status: reqwest::StatusCode) -> Result<(), Box<dyn std::error::Error>> {
+    if status != reqwest::StatusCode::PARTIAL_CONTENT {
+        error!(%status, "Range resume request did not return 206 Partial Content");
+        return Err(format!(
+            "expected 206 Partial Content resuming download, got {} (server may not support Range)",
+            status
+        )
+        .into());
+    }
+    Ok(())
+}
 
+/// Stream `response`'s body to `temp_file` one chunk at a time, so peak
+/// memory is bounded by a single chunk rather than the full file. If the
+/// connection drops mid-stream, re-issue the request with a `Range`
+/// header starting from the last byte written and append, retrying up to
+/// [`MAX_RETRIES`] times with exponential backoff.
 async fn stream_to_file(
-    response: reqwest::Response,
+    client: &reqwest::Client,
+    url: &str,
+    mut response: reqwest::Response,
     temp_file: &NamedTempFile,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let mut file = tokio::fs::File::from_std(temp_file.reopen()?);
-    let bytes = response.bytes().await?;
-    
-    info!(size_mb = bytes.len() / (1024 * 1024), "Downloaded ZIP");
-    
-    file.write_all(&bytes).await?;
+    let mut bytes_written: u64 = 0;
+    let mut attempt = 0u32;
+    let mut stream = response.bytes_stream();
+
+    loop {
+        match stream.next().await {
+            Some(Ok(chunk)) => {
+                file.write_all(&chunk).await?;
+                bytes_written += chunk.len() as u64;
+            }
+            Some(Err(e)) => {
+                if attempt >= MAX_RETRIES {
+                    error!(attempt, bytes_written, "Giving up after max retries");
+                    return Err(Box::new(e));
+                }
+                attempt += 1;
+                let backoff = INITIAL_BACKOFF * 2u32.pow(attempt - 1);
+                warn!(
+                    error = %e, attempt, bytes_written, backoff_secs = backoff.as_secs(),
+                    "Download interrupted, resuming with Range after backoff"
+                );
+                tokio::time::sleep(backoff).await;
+
+                response = client
+                    .get(url)
+                    .header(reqwest::header::RANGE, format!("bytes={}-", bytes_written))
+                    .send()
+                    .await?;
+                validate_resume_status(response.status())?;
+                stream = response.bytes_stream();
+            }
+            None => break,
+        }
+    }
+
+    info!(size_mb = bytes_written / (1024 * 1024), "Downloaded ZIP");
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_resume_status_accepts_partial_content() {
+        assert!(validate_resume_status(reqwest::StatusCode::PARTIAL_CONTENT).is_ok());
+    }
+
+    #[test]
+    fn validate_resume_status_rejects_a_full_200_reply() {
+        // A server/proxy/CDN that ignores the Range header and resends the
+        // whole body must not be treated as a valid resume.
+        let result = validate_resume_status(reqwest::StatusCode::OK);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn validate_resume_status_rejects_other_error_statuses() {
+        assert!(validate_resume_status(reqwest::StatusCode::RANGE_NOT_SATISFIABLE).is_err());
+    }
+}
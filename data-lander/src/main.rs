@@ -1,13 +1,33 @@
-use aws_sdk_s3::Client;
 use dotenvy::dotenv;
-use log::{info, warn};
+use futures::stream::{self, StreamExt};
+use log::{error, info, warn};
 
 mod converter_uploader;
 mod downloader;
 mod platform_collector;
+mod source;
+mod storage;
 mod unzipper;
 mod utils;
 
+/// Whether a run should skip reprocessing because its outputs already
+/// exist at the destination, unless the caller forced a redo.
+fn should_skip_reprocessing(force_reprocess: bool, outputs_exist: bool) -> bool {
+    !force_reprocess && outputs_exist
+}
+
+/// Run `tasks` with at most `concurrency` running at once, collecting
+/// every task's result rather than aborting on the first failure.
+async fn run_with_bounded_concurrency<Fut>(
+    tasks: Vec<Fut>,
+    concurrency: usize,
+) -> Vec<Result<(), String>>
+where
+    Fut: std::future::Future<Output = Result<(), String>>,
+{
+    stream::iter(tasks).buffer_unordered(concurrency).collect().await
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Initialize logger
@@ -42,24 +62,78 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     };
 
-    // Initialize S3 config
-    println!("Initializing S3 config...");
-    let s3_config = utils::get_s3_config().await;
-    let s3_client = Client::from_conf(s3_config);
-
-    let s3_bucket: String = match std::env::var("S3_BUCKET_NAME") {
-        Ok(bucket) => {
-            println!("S3 bucket found: {}", bucket);
-            bucket
+    // Build the destination object-storage backend from its URL, e.g.
+    // `s3://bucket/prefix`, `gs://bucket/prefix`, `az://container/prefix`,
+    // or `file:///abs/path` for local testing.
+    let destination_url = match std::env::var("DESTINATION_URL") {
+        Ok(url) => {
+            println!("Destination URL found: {}", url);
+            url
         }
         Err(e) => {
-            eprintln!("S3_BUCKET_NAME environment variable not set: {}", e);
+            eprintln!("DESTINATION_URL environment variable not set: {}", e);
             return Err(Box::new(e) as Box<dyn std::error::Error>);
         }
     };
 
-    // Create a prefix based on the URL
-    let temp_file = downloader::download_zip_to_temp(&url).await?;
+    // Build the S3 client once up front and hand it to whichever of the
+    // source/destination builders below need it, so a run with both an
+    // `s3://` URL and an `s3://` DESTINATION_URL only resolves the
+    // credentials chain and retry policy a single time.
+    let s3_client = if url.starts_with("s3://") || destination_url.starts_with("s3://") {
+        Some(aws_sdk_s3::Client::from_conf(utils::get_s3_config().await))
+    } else {
+        None
+    };
+
+    println!("Initializing object storage backend...");
+    let (object_store, destination_prefix) =
+        storage::build_object_store(&destination_url, s3_client.clone())
+            .await
+            .map_err(|e| -> Box<dyn std::error::Error> { Box::new(e) })?;
+
+    // Output serialization format, defaulting to Parquet for backwards compatibility
+    let output_format: converter_uploader::OutputFormat = match std::env::var("OUTPUT_FORMAT") {
+        Ok(format) => format
+            .parse()
+            .map_err(|e: String| -> Box<dyn std::error::Error> { e.into() })?,
+        Err(_) => converter_uploader::OutputFormat::Parquet,
+    };
+
+    // How long a presigned download URL for an uploaded object stays valid
+    let presigned_url_expiry = std::time::Duration::from_secs(
+        std::env::var("PRESIGNED_URL_EXPIRY_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(3600),
+    );
+
+    // Skip the whole job if we've already produced this date's outputs,
+    // unless the caller explicitly wants to redo the work.
+    let key_prefix = format!("{}{}", destination_prefix, utils::get_s3_prefix(&url)?);
+    let force_reprocess = std::env::var("FORCE_REPROCESS").unwrap_or_default() == "true";
+    let outputs_exist = object_store
+        .prefix_exists(&key_prefix)
+        .await
+        .map_err(|e| -> Box<dyn std::error::Error> { Box::new(e) })?;
+
+    if should_skip_reprocessing(force_reprocess, outputs_exist) {
+        println!(
+            "Outputs already exist under {}, skipping (set FORCE_REPROCESS=true to redo)",
+            key_prefix
+        );
+        return Ok(());
+    }
+
+    // Fetch the source archive - the URL's scheme selects HTTP(S),
+    // S3, GCS, or Azure Blob as the source.
+    let archive_source = source::build_archive_source(&url, s3_client)
+        .await
+        .map_err(|e| -> Box<dyn std::error::Error> { Box::new(e) })?;
+    let temp_file = archive_source
+        .fetch_to_temp()
+        .await
+        .map_err(|e| -> Box<dyn std::error::Error> { Box::new(e) })?;
 
     info!(
         "File size: {:.2} MB downloaded to {:?}",
@@ -71,30 +145,81 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let extract_dir = tempfile::tempdir()?;
     let extracted_files = unzipper::streamed_unzip(&temp_file, extract_dir.path())?;
 
-    // Process each extracted file
-    for file_path in &extracted_files {
-        info!("Processing: {:?}", file_path);
-
-        if file_path.extension().and_then(|s| s.to_str()) == Some("csv") {
-            // This function will filter the CSV by ALLOWED_VLOPS and upload it to S3 as Parquet
-            // Handles -> Conversion, Filtering, and Uploading
-            // SRP nightmare, but it works
-            converter_uploader::convert_filter_and_upload_direct(
-                file_path,
-                &s3_client,
-                &s3_bucket,
-                utils::get_s3_prefix(&url)?.as_str(),
-            )
-            .await
-            .map_err(|e| -> Box<dyn std::error::Error> { Box::new(e) })?;
-        } else {
-            warn!("Non non-CSV file found, skipping...: {:?}", file_path);
-        }
+    let csv_files: Vec<_> = extracted_files
+        .iter()
+        .filter(|file_path| {
+            let is_csv = file_path.extension().and_then(|s| s.to_str()) == Some("csv");
+            if !is_csv {
+                warn!("Non-CSV file found, skipping...: {:?}", file_path);
+            }
+            is_csv
+        })
+        .cloned()
+        .collect();
+
+    // I/O-bound conversions run with bounded concurrency instead of one
+    // at a time, so a `global-full` archive with dozens of CSVs doesn't
+    // serialize on each individual CSV->Parquet->storage round trip. One
+    // failing file is logged and the rest continue; we exit non-zero
+    // afterwards if anything failed, rather than aborting on first error.
+    let concurrency: usize = std::env::var("MAX_CONCURRENT_FILES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(4);
+
+    let tasks: Vec<_> = csv_files
+        .iter()
+        .map(|file_path| {
+            let object_store = object_store.clone();
+            let key_prefix = key_prefix.clone();
+            async move {
+                info!("Processing: {:?}", file_path);
+
+                // This function will filter the CSV by ALLOWED_VLOPS and upload it
+                // Handles -> Conversion, Filtering, and Uploading
+                // SRP nightmare, but it works
+                let outcome = converter_uploader::convert_filter_and_upload_direct(
+                    file_path,
+                    &object_store,
+                    key_prefix.as_str(),
+                    output_format,
+                    presigned_url_expiry,
+                )
+                .await
+                .map_err(|e| format!("{:?}: {}", file_path, e))?;
+
+                if let Some(url) = outcome.and_then(|o| o.download_url) {
+                    info!("Download URL for {:?}: {}", file_path, url);
+                }
+
+                Ok(())
+            }
+        })
+        .collect();
+
+    let results = run_with_bounded_concurrency(tasks, concurrency).await;
+
+    let failures: Vec<String> = results.into_iter().filter_map(Result::err).collect();
+    for failure in &failures {
+        error!("Failed to process file: {}", failure);
+    }
+
+    println!("✅ Data processing completed");
+    println!(
+        "Processed {} files total, {} failed",
+        extracted_files.len(),
+        failures.len()
+    );
+
+    if !failures.is_empty() {
+        return Err(format!(
+            "{} of {} CSV file(s) failed to process",
+            failures.len(),
+            csv_files.len()
+        )
+        .into());
     }
 
-    println!("✅ Data processing completed successfully!");
-    println!("Processed {} files total", extracted_files.len());
-    
     // Check if this should be a one-time job or keep running
     if std::env::var("KEEP_ALIVE").unwrap_or_default() == "true" {
         println!("KEEP_ALIVE=true, entering sleep mode...");
@@ -108,3 +233,61 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn should_skip_reprocessing_only_when_outputs_exist_and_not_forced() {
+        assert!(should_skip_reprocessing(false, true));
+        assert!(!should_skip_reprocessing(true, true));
+        assert!(!should_skip_reprocessing(false, false));
+        assert!(!should_skip_reprocessing(true, false));
+    }
+
+    #[tokio::test]
+    async fn run_with_bounded_concurrency_collects_failures_without_aborting() {
+        let tasks: Vec<_> = (0..5)
+            .map(|i| async move {
+                if i % 2 == 0 {
+                    Ok(())
+                } else {
+                    Err(format!("task {} failed", i))
+                }
+            })
+            .collect();
+
+        let results = run_with_bounded_concurrency(tasks, 2).await;
+
+        assert_eq!(results.len(), 5);
+        assert_eq!(results.iter().filter(|r| r.is_ok()).count(), 3);
+        assert_eq!(results.iter().filter(|r| r.is_err()).count(), 2);
+    }
+
+    #[tokio::test]
+    async fn run_with_bounded_concurrency_never_exceeds_the_limit() {
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let max_observed = Arc::new(AtomicUsize::new(0));
+
+        let tasks: Vec<_> = (0..10)
+            .map(|_| {
+                let in_flight = in_flight.clone();
+                let max_observed = max_observed.clone();
+                async move {
+                    let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_observed.fetch_max(current, Ordering::SeqCst);
+                    tokio::task::yield_now().await;
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                    Ok(())
+                }
+            })
+            .collect();
+
+        run_with_bounded_concurrency(tasks, 3).await;
+
+        assert!(max_observed.load(Ordering::SeqCst) <= 3);
+    }
+}
@@ -191,22 +191,19 @@ impl PlatformDataCollector {
             return Ok(dataframes[0].clone());
         }
 
-        // Concatenate multiple DataFrames
-        let mut consolidated = dataframes[0].clone();
-        for df in &dataframes[1..] {
-            consolidated = consolidated
-                .lazy()
-                .with_columns([
-                    // Ensure all columns are present in both DataFrames
-                    col("*")
-                ])
-                .collect()
-                .map_err(CsvProcessingError::PolarsError)?
-                .vstack(df)
-                .map_err(CsvProcessingError::PolarsError)?;
-        }
+        // Diagonally concat all DataFrames in one pass, aligning by column
+        // name and filling any column missing from a given monthly export
+        // with nulls. Unlike `vstack`, this tolerates column sets/order
+        // drifting between reporting periods, and collecting once avoids
+        // the O(n^2) rebuild of repeatedly re-stacking one frame at a time.
+        let lazy_frames: Vec<LazyFrame> = dataframes.iter().map(|df| df.clone().lazy()).collect();
+
+        let consolidated = concat_lf_diagonal(&lazy_frames, UnionArgs::default())
+            .map_err(CsvProcessingError::PolarsError)?
+            .collect()
+            .map_err(CsvProcessingError::PolarsError)?;
 
-        info!("Consolidated platform '{}' data: {} total rows", 
+        info!("Consolidated platform '{}' data: {} total rows",
               platform, consolidated.height());
 
         Ok(consolidated)
@@ -274,6 +271,35 @@ mod tests {
         assert_eq!(collector.total_dataframe_count(), 0);
     }
 
+    #[test]
+    fn test_consolidate_tolerates_schema_drift_between_exports() {
+        let allowed_platforms = vec!["Facebook".to_string()];
+        let mut collector = PlatformDataCollector::new(allowed_platforms);
+
+        // January export: no `region` column yet.
+        let mut january = NamedTempFile::new().unwrap();
+        writeln!(january, "platform_name,data").unwrap();
+        writeln!(january, "Facebook,1").unwrap();
+        january.flush().unwrap();
+        collector.add_csv_data(january.path()).unwrap();
+
+        // February export: `region` column added, column order differs.
+        let mut february = NamedTempFile::new().unwrap();
+        writeln!(february, "data,platform_name,region").unwrap();
+        writeln!(february, "2,Facebook,EU").unwrap();
+        february.flush().unwrap();
+        collector.add_csv_data(february.path()).unwrap();
+
+        let consolidated = collector.consolidate_platform_data("facebook").unwrap();
+
+        assert_eq!(consolidated.height(), 2);
+        assert!(consolidated.get_column_names().contains(&&PlSmallStr::from_static("region")));
+
+        let region = consolidated.column("region").unwrap();
+        let nulls = region.null_count();
+        assert_eq!(nulls, 1, "the January row should be null-filled for the region column it never had");
+    }
+
     #[test]
     fn test_empty_csv_handling() {
         let allowed_platforms = vec!["Facebook".to_string()];
@@ -0,0 +1,278 @@
+//! Abstracts where the source archive comes from, so the pipeline isn't
+//! limited to speaking HTTPS via `reqwest`. The scheme of the `URL` env
+//! var selects the implementation: `http(s)://` (the original behavior),
+//! or a private `s3://`, `gs://`, `az://` location that already mirrors
+//! the DSA archive.
+
+use async_trait::async_trait;
+use aws_sdk_s3::Client as S3Client;
+use std::fmt;
+use tempfile::NamedTempFile;
+use tokio::io::AsyncWriteExt;
+
+#[derive(Debug)]
+pub enum SourceError {
+    Download(Box<dyn std::error::Error + Send + Sync>),
+    Backend(String),
+    UnsupportedScheme(String),
+    InvalidSource(String),
+}
+
+impl fmt::Display for SourceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SourceError::Download(e) => write!(f, "archive download failed: {}", e),
+            SourceError::Backend(msg) => write!(f, "archive source backend error: {}", msg),
+            SourceError::UnsupportedScheme(scheme) => {
+                write!(f, "unsupported archive source scheme: '{}'", scheme)
+            }
+            SourceError::InvalidSource(msg) => write!(f, "invalid archive source URL: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for SourceError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SourceError::Download(e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+/// Fetches the source archive into a local temp file, regardless of
+/// where it actually lives.
+#[async_trait]
+pub trait ArchiveSource: Send + Sync {
+    async fn fetch_to_temp(&self) -> Result<NamedTempFile, SourceError>;
+}
+
+/// `http(s)://` backend — wraps the original `downloader` module.
+pub struct HttpSource {
+    url: String,
+}
+
+impl HttpSource {
+    pub fn new(url: String) -> Self {
+        Self { url }
+    }
+}
+
+#[async_trait]
+impl ArchiveSource for HttpSource {
+    async fn fetch_to_temp(&self) -> Result<NamedTempFile, SourceError> {
+        crate::downloader::download_zip_to_temp(&self.url)
+            .await
+            .map_err(|e| SourceError::Download(e.to_string().into()))
+    }
+}
+
+/// `s3://bucket/key` backend.
+pub struct S3Source {
+    client: S3Client,
+    bucket: String,
+    key: String,
+}
+
+impl S3Source {
+    pub fn new(client: S3Client, bucket: String, key: String) -> Self {
+        Self { client, bucket, key }
+    }
+}
+
+#[async_trait]
+impl ArchiveSource for S3Source {
+    async fn fetch_to_temp(&self) -> Result<NamedTempFile, SourceError> {
+        let object = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(&self.key)
+            .send()
+            .await
+            .map_err(|e| {
+                SourceError::Backend(format!(
+                    "S3 get_object failed for s3://{}/{}: {}",
+                    self.bucket, self.key, e
+                ))
+            })?;
+
+        let bytes = object.body.collect().await.map_err(|e| {
+            SourceError::Backend(format!(
+                "failed reading S3 object body for s3://{}/{}: {}",
+                self.bucket, self.key, e
+            ))
+        })?;
+
+        write_to_temp(bytes.into_bytes().as_ref()).await
+    }
+}
+
+/// `gs://bucket/object` backend.
+pub struct GcsSource {
+    client: google_cloud_storage::client::Client,
+    bucket: String,
+    object: String,
+}
+
+impl GcsSource {
+    pub async fn new(bucket: String, object: String) -> Result<Self, SourceError> {
+        let config = google_cloud_storage::client::ClientConfig::default()
+            .with_auth()
+            .await
+            .map_err(|e| SourceError::Backend(format!("failed to load GCS credentials: {}", e)))?;
+        Ok(Self {
+            client: google_cloud_storage::client::Client::new(config),
+            bucket,
+            object,
+        })
+    }
+}
+
+#[async_trait]
+impl ArchiveSource for GcsSource {
+    async fn fetch_to_temp(&self) -> Result<NamedTempFile, SourceError> {
+        use google_cloud_storage::http::objects::download::Range;
+        use google_cloud_storage::http::objects::get::GetObjectRequest;
+
+        let bytes = self
+            .client
+            .download_object(
+                &GetObjectRequest {
+                    bucket: self.bucket.clone(),
+                    object: self.object.clone(),
+                    ..Default::default()
+                },
+                &Range::default(),
+            )
+            .await
+            .map_err(|e| {
+                SourceError::Backend(format!(
+                    "GCS download failed for gs://{}/{}: {}",
+                    self.bucket, self.object, e
+                ))
+            })?;
+
+        write_to_temp(&bytes).await
+    }
+}
+
+/// `az://container/blob` backend.
+pub struct AzureSource {
+    container_client: azure_storage_blobs::prelude::ContainerClient,
+    blob: String,
+}
+
+impl AzureSource {
+    pub fn new(container: String, blob: String) -> Result<Self, SourceError> {
+        let account = std::env::var("AZURE_STORAGE_ACCOUNT")
+            .map_err(|_| SourceError::Backend("AZURE_STORAGE_ACCOUNT not set".to_string()))?;
+        let access_key = std::env::var("AZURE_STORAGE_ACCESS_KEY")
+            .map_err(|_| SourceError::Backend("AZURE_STORAGE_ACCESS_KEY not set".to_string()))?;
+        let credentials = azure_storage::StorageCredentials::access_key(account.clone(), access_key);
+        let container_client =
+            azure_storage_blobs::prelude::ClientBuilder::new(account, credentials).container_client(container);
+        Ok(Self { container_client, blob })
+    }
+}
+
+#[async_trait]
+impl ArchiveSource for AzureSource {
+    async fn fetch_to_temp(&self) -> Result<NamedTempFile, SourceError> {
+        let bytes = self
+            .container_client
+            .blob_client(&self.blob)
+            .get_content()
+            .await
+            .map_err(|e| SourceError::Backend(format!("Azure Blob download failed for {}: {}", self.blob, e)))?;
+
+        write_to_temp(&bytes).await
+    }
+}
+
+async fn write_to_temp(bytes: &[u8]) -> Result<NamedTempFile, SourceError> {
+    let temp_file = NamedTempFile::with_suffix(".zip")
+        .map_err(|e| SourceError::Backend(format!("failed to create temp file: {}", e)))?;
+    let mut file = tokio::fs::File::from_std(
+        temp_file
+            .reopen()
+            .map_err(|e| SourceError::Backend(format!("failed to reopen temp file: {}", e)))?,
+    );
+    file.write_all(bytes)
+        .await
+        .map_err(|e| SourceError::Backend(format!("failed to write temp file: {}", e)))?;
+    Ok(temp_file)
+}
+
+/// Parse `URL` and dispatch on its scheme to the matching [`ArchiveSource`].
+///
+/// `s3_client` serves the same purpose as the identically-named parameter
+/// on [`storage::build_object_store`](crate::storage::build_object_store) —
+/// see its doc comment for why it's threaded through instead of always
+/// building a fresh client here.
+pub async fn build_archive_source(
+    url: &str,
+    s3_client: Option<S3Client>,
+) -> Result<Box<dyn ArchiveSource>, SourceError> {
+    if url.starts_with("http://") || url.starts_with("https://") {
+        return Ok(Box::new(HttpSource::new(url.to_string())));
+    }
+
+    let (scheme, rest) = url
+        .split_once("://")
+        .ok_or_else(|| SourceError::InvalidSource(format!("missing '://' scheme in '{}'", url)))?;
+
+    match scheme {
+        "s3" => {
+            let (bucket, key) = split_bucket_and_key(rest)?;
+            let client = match s3_client {
+                Some(client) => client,
+                None => S3Client::from_conf(crate::utils::get_s3_config().await),
+            };
+            Ok(Box::new(S3Source::new(client, bucket, key)))
+        }
+        "gs" => {
+            let (bucket, object) = split_bucket_and_key(rest)?;
+            Ok(Box::new(GcsSource::new(bucket, object).await?))
+        }
+        "az" => {
+            let (container, blob) = split_bucket_and_key(rest)?;
+            Ok(Box::new(AzureSource::new(container, blob)?))
+        }
+        other => Err(SourceError::UnsupportedScheme(other.to_string())),
+    }
+}
+
+/// Split `bucket/key` into the bucket (or container) name and object key.
+fn split_bucket_and_key(rest: &str) -> Result<(String, String), SourceError> {
+    rest.split_once('/')
+        .map(|(bucket, key)| (bucket.to_string(), key.to_string()))
+        .ok_or_else(|| SourceError::InvalidSource(format!("missing object key in '{}'", rest)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_bucket_and_key_splits_on_first_slash() {
+        let (bucket, key) = split_bucket_and_key("my-bucket/path/to/archive.zip").unwrap();
+        assert_eq!(bucket, "my-bucket");
+        assert_eq!(key, "path/to/archive.zip");
+    }
+
+    #[test]
+    fn split_bucket_and_key_rejects_missing_key() {
+        assert!(matches!(
+            split_bucket_and_key("my-bucket"),
+            Err(SourceError::InvalidSource(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn write_to_temp_round_trips_bytes() {
+        let temp_file = write_to_temp(b"zip bytes").await.unwrap();
+        let written = std::fs::read(temp_file.path()).unwrap();
+        assert_eq!(written, b"zip bytes");
+    }
+}
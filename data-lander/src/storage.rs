@@ -0,0 +1,555 @@
+//! Pluggable object-storage backends behind a single `ObjectStore` trait.
+//!
+//! The upload path talks to whichever backend a destination URL selects
+//! (`s3://bucket/prefix`, `gs://bucket/prefix`, `az://container/prefix`,
+//! `file:///abs/path`) instead of a concrete `aws_sdk_s3::Client`, so the
+//! same pipeline can target AWS S3, an S3-compatible store (Garage,
+//! MinIO), Google Cloud Storage, Azure Blob, or a local directory for
+//! testing, with no branching in the pipeline itself.
+
+use async_trait::async_trait;
+use aws_sdk_s3::{presigning::PresigningConfig, primitives::ByteStream, Client as S3Client};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncReadExt;
+
+/// Error returned by an [`ObjectStore`] backend.
+///
+/// Backends normalize their own SDK-specific errors into this type, so
+/// callers no longer need to branch on e.g. `SdkError` status codes.
+#[derive(Debug)]
+pub enum StorageError {
+    Backend(String),
+    UnsupportedScheme(String),
+    InvalidDestination(String),
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::Backend(msg) => write!(f, "storage backend error: {}", msg),
+            StorageError::UnsupportedScheme(scheme) => {
+                write!(f, "unsupported storage scheme: '{}'", scheme)
+            }
+            StorageError::InvalidDestination(msg) => write!(f, "invalid destination URL: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for StorageError {}
+
+/// Unifies AWS S3, GCS, Azure Blob, and the local filesystem behind a
+/// single `put` interface.
+#[async_trait]
+pub trait ObjectStore: Send + Sync {
+    /// Write `bytes` to `key`, overwriting any existing object.
+    async fn put(&self, key: &str, bytes: Vec<u8>) -> Result<(), StorageError>;
+
+    /// Upload the file at `source` to `key` as a series of `part_size`-byte
+    /// parts read off disk as they're sent, rather than one request, so a
+    /// multi-GB output never sits fully buffered in memory.
+    ///
+    /// Backends without a native multipart API fall back to [`Self::put`],
+    /// reading the whole file into memory to do so; the default
+    /// implementation here does exactly that.
+    async fn put_multipart(
+        &self,
+        key: &str,
+        source: &Path,
+        part_size: usize,
+    ) -> Result<(), StorageError> {
+        let _ = part_size;
+        let bytes = tokio::fs::read(source).await.map_err(|e| {
+            StorageError::Backend(format!("failed to read {} for upload: {}", source.display(), e))
+        })?;
+        self.put(key, bytes).await
+    }
+
+    /// Generate a time-limited, shareable GET URL for `key`, letting a
+    /// caller hand the uploaded object to another service without
+    /// distributing bucket credentials.
+    ///
+    /// Returns `Ok(None)` for backends that don't support presigning
+    /// (GCS/Azure/local here) rather than erroring, since presigning is an
+    /// optional convenience on top of the core put contract, not every
+    /// backend's job to provide.
+    async fn presign_get(
+        &self,
+        key: &str,
+        expires_in: Duration,
+    ) -> Result<Option<String>, StorageError> {
+        let _ = (key, expires_in);
+        Ok(None)
+    }
+
+    /// Whether any object already exists under `prefix`, so a run can
+    /// skip reprocessing outputs it already produced.
+    ///
+    /// Backends that can't answer cheaply return `Ok(false)` (i.e. "not
+    /// known to exist") rather than erroring, so idempotency is a
+    /// best-effort optimization, not a correctness requirement.
+    async fn prefix_exists(&self, prefix: &str) -> Result<bool, StorageError> {
+        let _ = prefix;
+        Ok(false)
+    }
+}
+
+/// `s3://` backend. Also serves S3-compatible stores (Garage, MinIO) when
+/// the underlying client is configured with a custom endpoint URL.
+pub struct S3Store {
+    client: S3Client,
+    bucket: String,
+}
+
+impl S3Store {
+    pub fn new(client: S3Client, bucket: String) -> Self {
+        Self { client, bucket }
+    }
+}
+
+/// S3 requires every part but the last to be at least 5 MiB.
+const S3_MIN_PART_SIZE: usize = 5 * 1024 * 1024;
+
+#[async_trait]
+impl ObjectStore for S3Store {
+    async fn put(&self, key: &str, bytes: Vec<u8>) -> Result<(), StorageError> {
+        self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .body(ByteStream::from(bytes))
+            .send()
+            .await
+            .map_err(|e| {
+                StorageError::Backend(format!(
+                    "S3 put_object failed for s3://{}/{}: {}",
+                    self.bucket, key, e
+                ))
+            })?;
+        Ok(())
+    }
+
+    async fn put_multipart(
+        &self,
+        key: &str,
+        source: &Path,
+        part_size: usize,
+    ) -> Result<(), StorageError> {
+        let part_size = part_size.max(S3_MIN_PART_SIZE);
+
+        let create = self
+            .client
+            .create_multipart_upload()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(|e| {
+                StorageError::Backend(format!(
+                    "S3 create_multipart_upload failed for s3://{}/{}: {}",
+                    self.bucket, key, e
+                ))
+            })?;
+        let upload_id = create.upload_id().ok_or_else(|| {
+            StorageError::Backend(format!(
+                "S3 create_multipart_upload returned no upload id for s3://{}/{}",
+                self.bucket, key
+            ))
+        })?;
+
+        let result = self.upload_parts(key, upload_id, source, part_size).await;
+
+        match result {
+            Ok(completed_parts) => {
+                self.client
+                    .complete_multipart_upload()
+                    .bucket(&self.bucket)
+                    .key(key)
+                    .upload_id(upload_id)
+                    .multipart_upload(
+                        aws_sdk_s3::types::CompletedMultipartUpload::builder()
+                            .set_parts(Some(completed_parts))
+                            .build(),
+                    )
+                    .send()
+                    .await
+                    .map_err(|e| {
+                        StorageError::Backend(format!(
+                            "S3 complete_multipart_upload failed for s3://{}/{}: {}",
+                            self.bucket, key, e
+                        ))
+                    })?;
+                Ok(())
+            }
+            Err(err) => {
+                // Best-effort cleanup so failed uploads don't linger and
+                // accrue storage charges; the original error still wins.
+                let _ = self
+                    .client
+                    .abort_multipart_upload()
+                    .bucket(&self.bucket)
+                    .key(key)
+                    .upload_id(upload_id)
+                    .send()
+                    .await;
+                Err(err)
+            }
+        }
+    }
+
+    async fn presign_get(
+        &self,
+        key: &str,
+        expires_in: Duration,
+    ) -> Result<Option<String>, StorageError> {
+        let presign_config = PresigningConfig::expires_in(expires_in).map_err(|e| {
+            StorageError::Backend(format!("invalid presign expiry for {}: {}", key, e))
+        })?;
+
+        let presigned = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .presigned(presign_config)
+            .await
+            .map_err(|e| {
+                StorageError::Backend(format!(
+                    "failed to presign GET for s3://{}/{}: {}",
+                    self.bucket, key, e
+                ))
+            })?;
+
+        Ok(Some(presigned.uri().to_string()))
+    }
+
+    async fn prefix_exists(&self, prefix: &str) -> Result<bool, StorageError> {
+        let listing = self
+            .client
+            .list_objects_v2()
+            .bucket(&self.bucket)
+            .prefix(prefix)
+            .max_keys(1)
+            .send()
+            .await
+            .map_err(|e| {
+                StorageError::Backend(format!(
+                    "S3 list_objects_v2 failed for s3://{}/{}: {}",
+                    self.bucket, prefix, e
+                ))
+            })?;
+
+        Ok(listing.key_count().unwrap_or(0) > 0)
+    }
+}
+
+impl S3Store {
+    /// Read `source` in `part_size` chunks, uploading each part as soon as
+    /// it's read so at most one part ever sits in memory at a time, rather
+    /// than buffering the whole file before the first part goes out.
+    async fn upload_parts(
+        &self,
+        key: &str,
+        upload_id: &str,
+        source: &Path,
+        part_size: usize,
+    ) -> Result<Vec<aws_sdk_s3::types::CompletedPart>, StorageError> {
+        let mut file = tokio::fs::File::open(source).await.map_err(|e| {
+            StorageError::Backend(format!(
+                "failed to open {} for multipart upload: {}",
+                source.display(),
+                e
+            ))
+        })?;
+
+        let mut completed_parts = Vec::new();
+        let mut part_number = 0i32;
+
+        loop {
+            let mut buf = vec![0u8; part_size];
+            let mut filled = 0;
+            while filled < buf.len() {
+                let n = file.read(&mut buf[filled..]).await.map_err(|e| {
+                    StorageError::Backend(format!(
+                        "failed to read {} for multipart upload: {}",
+                        source.display(),
+                        e
+                    ))
+                })?;
+                if n == 0 {
+                    break;
+                }
+                filled += n;
+            }
+            if filled == 0 {
+                break;
+            }
+            buf.truncate(filled);
+
+            part_number += 1;
+            let upload_part = self
+                .client
+                .upload_part()
+                .bucket(&self.bucket)
+                .key(key)
+                .upload_id(upload_id)
+                .part_number(part_number)
+                .body(ByteStream::from(buf))
+                .send()
+                .await
+                .map_err(|e| {
+                    StorageError::Backend(format!(
+                        "S3 upload_part {} failed for s3://{}/{}: {}",
+                        part_number, self.bucket, key, e
+                    ))
+                })?;
+
+            let e_tag = upload_part.e_tag().ok_or_else(|| {
+                StorageError::Backend(format!(
+                    "S3 upload_part {} returned no ETag for s3://{}/{}",
+                    part_number, self.bucket, key
+                ))
+            })?;
+
+            completed_parts.push(
+                aws_sdk_s3::types::CompletedPart::builder()
+                    .part_number(part_number)
+                    .e_tag(e_tag)
+                    .build(),
+            );
+
+            if filled < part_size {
+                break;
+            }
+        }
+
+        Ok(completed_parts)
+    }
+}
+
+/// `gs://` backend.
+pub struct GcsStore {
+    client: google_cloud_storage::client::Client,
+    bucket: String,
+}
+
+impl GcsStore {
+    pub async fn new(bucket: String) -> Result<Self, StorageError> {
+        let config = google_cloud_storage::client::ClientConfig::default()
+            .with_auth()
+            .await
+            .map_err(|e| StorageError::Backend(format!("failed to load GCS credentials: {}", e)))?;
+        Ok(Self {
+            client: google_cloud_storage::client::Client::new(config),
+            bucket,
+        })
+    }
+}
+
+#[async_trait]
+impl ObjectStore for GcsStore {
+    async fn put(&self, key: &str, bytes: Vec<u8>) -> Result<(), StorageError> {
+        use google_cloud_storage::http::objects::upload::{Media, UploadObjectRequest, UploadType};
+
+        let upload_type = UploadType::Simple(Media::new(key.to_string()));
+        self.client
+            .upload_object(
+                &UploadObjectRequest {
+                    bucket: self.bucket.clone(),
+                    ..Default::default()
+                },
+                bytes,
+                &upload_type,
+            )
+            .await
+            .map_err(|e| {
+                StorageError::Backend(format!(
+                    "GCS upload failed for gs://{}/{}: {}",
+                    self.bucket, key, e
+                ))
+            })?;
+        Ok(())
+    }
+}
+
+/// `az://` backend.
+pub struct AzureStore {
+    container_client: azure_storage_blobs::prelude::ContainerClient,
+}
+
+impl AzureStore {
+    pub async fn new(container: String) -> Result<Self, StorageError> {
+        let account = std::env::var("AZURE_STORAGE_ACCOUNT")
+            .map_err(|_| StorageError::Backend("AZURE_STORAGE_ACCOUNT not set".to_string()))?;
+        let access_key = std::env::var("AZURE_STORAGE_ACCESS_KEY")
+            .map_err(|_| StorageError::Backend("AZURE_STORAGE_ACCESS_KEY not set".to_string()))?;
+        let credentials = azure_storage::StorageCredentials::access_key(account.clone(), access_key);
+        let container_client =
+            azure_storage_blobs::prelude::ClientBuilder::new(account, credentials).container_client(container);
+        Ok(Self { container_client })
+    }
+}
+
+#[async_trait]
+impl ObjectStore for AzureStore {
+    async fn put(&self, key: &str, bytes: Vec<u8>) -> Result<(), StorageError> {
+        self.container_client
+            .blob_client(key)
+            .put_block_blob(bytes)
+            .await
+            .map_err(|e| StorageError::Backend(format!("Azure Blob upload failed for {}: {}", key, e)))?;
+        Ok(())
+    }
+}
+
+/// Local filesystem backend (`file://`), mainly for local runs and tests.
+pub struct LocalStore {
+    root: PathBuf,
+}
+
+impl LocalStore {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+}
+
+#[async_trait]
+impl ObjectStore for LocalStore {
+    async fn put(&self, key: &str, bytes: Vec<u8>) -> Result<(), StorageError> {
+        let path = self.root.join(key);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| StorageError::Backend(format!("failed to create {}: {}", parent.display(), e)))?;
+        }
+        tokio::fs::write(&path, bytes)
+            .await
+            .map_err(|e| StorageError::Backend(format!("failed to write {}: {}", path.display(), e)))
+    }
+
+    async fn prefix_exists(&self, prefix: &str) -> Result<bool, StorageError> {
+        let dir = self.root.join(prefix);
+        match tokio::fs::read_dir(&dir).await {
+            Ok(mut entries) => Ok(entries
+                .next_entry()
+                .await
+                .map_err(|e| StorageError::Backend(format!("failed to read {}: {}", dir.display(), e)))?
+                .is_some()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(StorageError::Backend(format!("failed to read {}: {}", dir.display(), e))),
+        }
+    }
+}
+
+/// Parse a destination URL into the `ObjectStore` backend it selects and
+/// the key prefix within that backend (the path segment after the
+/// bucket/container, if any).
+///
+/// `s3_client` lets the caller hand in an already-built
+/// `aws_sdk_s3::Client` (e.g. the one built for the archive source) so a
+/// run with both an `s3://` source and an `s3://` destination doesn't
+/// resolve the credentials chain and retry policy twice; a fresh client
+/// is only built here when none is supplied.
+pub async fn build_object_store(
+    destination: &str,
+    s3_client: Option<S3Client>,
+) -> Result<(Arc<dyn ObjectStore>, String), StorageError> {
+    let (scheme, rest) = destination.split_once("://").ok_or_else(|| {
+        StorageError::InvalidDestination(format!("missing '://' scheme in '{}'", destination))
+    })?;
+
+    match scheme {
+        "s3" => {
+            let (bucket, prefix) = split_bucket_and_prefix(rest)?;
+            let client = match s3_client {
+                Some(client) => client,
+                None => S3Client::from_conf(crate::utils::get_s3_config().await),
+            };
+            Ok((Arc::new(S3Store::new(client, bucket)) as Arc<dyn ObjectStore>, prefix))
+        }
+        "gs" => {
+            let (bucket, prefix) = split_bucket_and_prefix(rest)?;
+            Ok((Arc::new(GcsStore::new(bucket).await?) as Arc<dyn ObjectStore>, prefix))
+        }
+        "az" => {
+            let (container, prefix) = split_bucket_and_prefix(rest)?;
+            Ok((Arc::new(AzureStore::new(container).await?) as Arc<dyn ObjectStore>, prefix))
+        }
+        "file" => Ok((Arc::new(LocalStore::new(PathBuf::from(rest))) as Arc<dyn ObjectStore>, String::new())),
+        other => Err(StorageError::UnsupportedScheme(other.to_string())),
+    }
+}
+
+/// Split `bucket/prefix/…` into the bucket (or container) name and a
+/// normalized `prefix/` (empty if there's no path segment).
+fn split_bucket_and_prefix(rest: &str) -> Result<(String, String), StorageError> {
+    if rest.is_empty() {
+        return Err(StorageError::InvalidDestination(
+            "destination URL is missing a bucket name".to_string(),
+        ));
+    }
+
+    match rest.split_once('/') {
+        Some((bucket, prefix)) if !prefix.is_empty() => {
+            Ok((bucket.to_string(), format!("{}/", prefix.trim_end_matches('/'))))
+        }
+        Some((bucket, _)) => Ok((bucket.to_string(), String::new())),
+        None => Ok((rest.to_string(), String::new())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_bucket_and_prefix_normalizes_trailing_slash() {
+        let (bucket, prefix) = split_bucket_and_prefix("my-bucket/a/b/").unwrap();
+        assert_eq!(bucket, "my-bucket");
+        assert_eq!(prefix, "a/b/");
+    }
+
+    #[test]
+    fn split_bucket_and_prefix_with_no_prefix() {
+        let (bucket, prefix) = split_bucket_and_prefix("my-bucket").unwrap();
+        assert_eq!(bucket, "my-bucket");
+        assert_eq!(prefix, "");
+    }
+
+    #[test]
+    fn split_bucket_and_prefix_rejects_empty() {
+        assert!(matches!(
+            split_bucket_and_prefix(""),
+            Err(StorageError::InvalidDestination(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn local_store_put_then_prefix_exists() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = LocalStore::new(dir.path().to_path_buf());
+
+        assert!(!store.prefix_exists("out/").await.unwrap());
+
+        store.put("out/data.parquet", b"some bytes".to_vec()).await.unwrap();
+
+        assert!(store.prefix_exists("out/").await.unwrap());
+        let written = std::fs::read(dir.path().join("out/data.parquet")).unwrap();
+        assert_eq!(written, b"some bytes");
+    }
+
+    #[tokio::test]
+    async fn presign_get_defaults_to_none_for_backends_without_presigning() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = LocalStore::new(dir.path().to_path_buf());
+
+        let presigned = store
+            .presign_get("out/data.parquet", Duration::from_secs(3600))
+            .await
+            .unwrap();
+
+        assert_eq!(presigned, None);
+    }
+}
@@ -1,14 +1,128 @@
+use std::fmt;
 use std::fs::File;
-use std::path::Path;
+use std::path::{Component, Path, PathBuf};
 use tempfile::NamedTempFile;
 use zip::ZipArchive;
-use std::path::PathBuf;
-use std::io::{copy};
+use std::io::copy;
 
+/// Limits enforced while recursively extracting a (possibly nested) zip
+/// archive, to guard against decompression bombs.
+#[derive(Debug, Clone, Copy)]
+pub struct UnzipLimits {
+    /// Maximum total uncompressed bytes written across the whole
+    /// extraction, including nested archives.
+    pub max_total_uncompressed_bytes: u64,
+    /// Reject an entry whose uncompressed size exceeds its compressed
+    /// size by more than this ratio (a hallmark of a crafted zip bomb).
+    pub max_compression_ratio: u64,
+    /// Maximum nesting depth for zips-within-zips.
+    pub max_nesting_depth: u32,
+}
+
+impl Default for UnzipLimits {
+    fn default() -> Self {
+        Self {
+            max_total_uncompressed_bytes: 10 * 1024 * 1024 * 1024, // 10 GiB
+            max_compression_ratio: 100,
+            max_nesting_depth: 8,
+        }
+    }
+}
+
+/// Error returned when extraction is aborted: by an I/O or zip archive
+/// failure, by an entry that would resolve outside the extraction root,
+/// or by one of the [`UnzipLimits`] being hit.
+#[derive(Debug)]
+pub enum UnzipError {
+    Io(std::io::Error),
+    Zip(zip::result::ZipError),
+    PathTraversal(String),
+    TotalSizeLimitExceeded { limit: u64 },
+    CompressionRatioExceeded { entry: String, ratio: u64, limit: u64 },
+    MaxNestingDepthExceeded { limit: u32 },
+}
+
+impl fmt::Display for UnzipError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnzipError::Io(e) => write!(f, "I/O error during extraction: {}", e),
+            UnzipError::Zip(e) => write!(f, "zip archive error: {}", e),
+            UnzipError::PathTraversal(entry) => {
+                write!(f, "refusing to extract '{}': resolves outside the extraction root", entry)
+            }
+            UnzipError::TotalSizeLimitExceeded { limit } => {
+                write!(f, "extraction aborted: total uncompressed size exceeded {} bytes", limit)
+            }
+            UnzipError::CompressionRatioExceeded { entry, ratio, limit } => write!(
+                f,
+                "extraction aborted: entry '{}' has compression ratio {} exceeding limit {} (possible zip bomb)",
+                entry, ratio, limit
+            ),
+            UnzipError::MaxNestingDepthExceeded { limit } => {
+                write!(f, "extraction aborted: nested zip depth exceeded limit of {}", limit)
+            }
+        }
+    }
+}
+
+impl std::error::Error for UnzipError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            UnzipError::Io(e) => Some(e),
+            UnzipError::Zip(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for UnzipError {
+    fn from(error: std::io::Error) -> Self {
+        UnzipError::Io(error)
+    }
+}
+
+impl From<zip::result::ZipError> for UnzipError {
+    fn from(error: zip::result::ZipError) -> Self {
+        UnzipError::Zip(error)
+    }
+}
+
+/// Extract `zip_file` into `extract_to`, recursing into nested zips, with
+/// the default [`UnzipLimits`].
 pub fn streamed_unzip(
     zip_file: &NamedTempFile,
     extract_to: &Path,
-) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+) -> Result<Vec<PathBuf>, UnzipError> {
+    streamed_unzip_with_limits(zip_file, extract_to, UnzipLimits::default())
+}
+
+/// Extract `zip_file` into `extract_to`, recursing into nested zips,
+/// enforcing `limits` across the whole (possibly nested) extraction.
+pub fn streamed_unzip_with_limits(
+    zip_file: &NamedTempFile,
+    extract_to: &Path,
+    limits: UnzipLimits,
+) -> Result<Vec<PathBuf>, UnzipError> {
+    let mut total_extracted_bytes = 0u64;
+    extract_recursive(zip_file, extract_to, &limits, 0, &mut total_extracted_bytes)
+}
+
+fn extract_recursive(
+    zip_file: &NamedTempFile,
+    extract_to: &Path,
+    limits: &UnzipLimits,
+    depth: u32,
+    total_extracted_bytes: &mut u64,
+) -> Result<Vec<PathBuf>, UnzipError> {
+    if depth > limits.max_nesting_depth {
+        return Err(UnzipError::MaxNestingDepthExceeded {
+            limit: limits.max_nesting_depth,
+        });
+    }
+
+    std::fs::create_dir_all(extract_to)?;
+    let canonical_root = extract_to.canonicalize()?;
+
     let file = File::open(zip_file.path())?;
     let mut archive = ZipArchive::new(file)?;
     let mut extracted_files = Vec::new();
@@ -16,16 +130,61 @@ pub fn streamed_unzip(
     for i in 0..archive.len() {
         let mut zip_file_entry = archive.by_index(i)?;
         let entry_name = zip_file_entry.name().to_owned(); // Store for later use
-        let outpath = extract_to.join(&entry_name);
 
-        if entry_name.ends_with('/') {
-            // It's a directory
+        // Reject `../` and absolute-path entries lexically before ever
+        // touching the filesystem (Zip Slip protection).
+        let outpath = safe_join(extract_to, &entry_name)?;
+
+        let is_dir = entry_name.ends_with('/');
+        if is_dir {
             std::fs::create_dir_all(&outpath)?;
+        } else if let Some(parent) = outpath.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        // Defense-in-depth: canonicalize the path we actually created and
+        // confirm it still resolves inside the extraction root. For a file
+        // entry, only the parent directory exists at this point (the file
+        // itself isn't written until below), so canonicalize the parent and
+        // re-join the file name rather than canonicalizing the not-yet-created
+        // file path.
+        let canonical_outpath = if is_dir {
+            outpath.canonicalize()?
+        } else {
+            let parent = outpath
+                .parent()
+                .ok_or_else(|| UnzipError::PathTraversal(entry_name.clone()))?;
+            let file_name = outpath
+                .file_name()
+                .ok_or_else(|| UnzipError::PathTraversal(entry_name.clone()))?;
+            parent.canonicalize()?.join(file_name)
+        };
+        if !canonical_outpath.starts_with(&canonical_root) {
+            return Err(UnzipError::PathTraversal(entry_name));
+        }
+
+        if is_dir {
             continue;
         }
 
-        if let Some(parent) = outpath.parent() {
-            std::fs::create_dir_all(parent)?;
+        // Decompression-bomb guard: bound both the per-entry compression
+        // ratio and the running total of uncompressed bytes written.
+        let compressed_size = zip_file_entry.compressed_size().max(1);
+        let uncompressed_size = zip_file_entry.size();
+        let ratio = uncompressed_size / compressed_size;
+        if ratio > limits.max_compression_ratio {
+            return Err(UnzipError::CompressionRatioExceeded {
+                entry: entry_name,
+                ratio,
+                limit: limits.max_compression_ratio,
+            });
+        }
+
+        *total_extracted_bytes += uncompressed_size;
+        if *total_extracted_bytes > limits.max_total_uncompressed_bytes {
+            return Err(UnzipError::TotalSizeLimitExceeded {
+                limit: limits.max_total_uncompressed_bytes,
+            });
         }
 
         let mut outfile = File::create(&outpath)?;
@@ -43,11 +202,82 @@ pub fn streamed_unzip(
             std::fs::copy(&outpath, temp_inner_zip.path())?; // Copy content to temp file
 
             // Recursively call streamed_unzip for the inner zip
-            let inner_extracted =
-                streamed_unzip(&temp_inner_zip, &outpath.with_extension(""))?; // Extract to a new directory named after the zip
+            let inner_extracted = extract_recursive(
+                &temp_inner_zip,
+                &outpath.with_extension(""), // Extract to a new directory named after the zip
+                limits,
+                depth + 1,
+                total_extracted_bytes,
+            )?;
             extracted_files.extend(inner_extracted); // Add extracted files from inner zip
         }
     }
 
     Ok(extracted_files)
-}
\ No newline at end of file
+}
+
+/// Join `entry_name` onto `root`, rejecting `..`/absolute-path components
+/// so a crafted entry name can't write outside `root` (Zip Slip).
+fn safe_join(root: &Path, entry_name: &str) -> Result<PathBuf, UnzipError> {
+    let mut joined = root.to_path_buf();
+    for component in Path::new(entry_name).components() {
+        match component {
+            Component::Normal(part) => joined.push(part),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                return Err(UnzipError::PathTraversal(entry_name.to_string()));
+            }
+        }
+    }
+    Ok(joined)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::FileOptions;
+    use zip::ZipWriter;
+
+    fn build_zip_with_file(entry_name: &str, contents: &[u8]) -> NamedTempFile {
+        let temp_zip = NamedTempFile::with_suffix(".zip").unwrap();
+        let mut writer = ZipWriter::new(temp_zip.reopen().unwrap());
+        writer
+            .start_file(entry_name, FileOptions::default())
+            .unwrap();
+        writer.write_all(contents).unwrap();
+        writer.finish().unwrap();
+        temp_zip
+    }
+
+    #[test]
+    fn extracts_a_regular_file_entry() {
+        let zip_file = build_zip_with_file("data.csv", b"a,b\n1,2\n");
+        let extract_dir = tempfile::tempdir().unwrap();
+
+        let extracted = streamed_unzip(&zip_file, extract_dir.path()).unwrap();
+
+        assert_eq!(extracted.len(), 1);
+        let contents = std::fs::read(&extracted[0]).unwrap();
+        assert_eq!(contents, b"a,b\n1,2\n");
+    }
+
+    #[test]
+    fn extracts_a_nested_file_entry_into_its_subdirectory() {
+        let zip_file = build_zip_with_file("nested/data.csv", b"hello");
+        let extract_dir = tempfile::tempdir().unwrap();
+
+        let extracted = streamed_unzip(&zip_file, extract_dir.path()).unwrap();
+
+        assert_eq!(extracted.len(), 1);
+        assert!(extracted[0].starts_with(extract_dir.path().join("nested")));
+        assert_eq!(std::fs::read(&extracted[0]).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn safe_join_rejects_parent_dir_traversal() {
+        let root = Path::new("/tmp/extract-root");
+        let result = safe_join(root, "../../etc/passwd");
+        assert!(matches!(result, Err(UnzipError::PathTraversal(_))));
+    }
+}
@@ -1,30 +1,119 @@
+use aws_config::ecs::EcsCredentialsProvider;
+use aws_config::environment::credentials::EnvironmentVariableCredentialsProvider;
+use aws_config::meta::credentials::CredentialsProviderChain;
+use aws_config::profile::ProfileFileCredentialsProvider;
+use aws_config::retry::RetryConfig;
 use aws_config::Region;
 use regex::Regex;
 use tempfile::NamedTempFile;
 
 // S3 Utils
 
+/// Default pattern for the `global-YYYY-MM-DD-{light,full}.zip` archive
+/// names DSA publishes today. Overridable via `URL_PATTERN` for archive
+/// naming schemes this pipeline doesn't know about yet (per-platform
+/// exports, per-day-range files, ...).
+const DEFAULT_URL_PATTERN: &str = r"global-(?P<date>\d{4}-\d{2}-\d{2})-(?P<variant>light|full)\.zip";
+
+/// Default output-prefix template, substituting the named capture groups
+/// from [`DEFAULT_URL_PATTERN`] (plus the date broken into
+/// `{year}`/`{month}`/`{day}`). Overridable via `S3_PREFIX_TEMPLATE`.
+const DEFAULT_PREFIX_TEMPLATE: &str = "global-{variant}/{year}-{month}-{day}/";
+
+/// Derive the destination key prefix for `url` by matching it against a
+/// (possibly operator-supplied) regex and substituting its named capture
+/// groups into a prefix template, so new archive naming schemes can be
+/// supported by setting `URL_PATTERN`/`S3_PREFIX_TEMPLATE` rather than
+/// recompiling.
+///
+/// The pattern is expected to expose a `date` group in `YYYY-MM-DD` form
+/// (split into `{year}`/`{month}`/`{day}` in the template) and may
+/// optionally expose `variant` and `platform` groups, substituted into
+/// `{variant}`/`{platform}` in the template when present.
 pub fn get_s3_prefix(url: &str) -> Result<String, Box<dyn std::error::Error>> {
-    let is_light_variant = is_light_variant(url)?;
-    let date = get_date_from_url(url)?;
-
-    let prefix = if is_light_variant {
-        format!("global-light/{:04}-{:02}-{:02}/", date[0], date[1], date[2])
-    } else {
-        format!("global-full/{:04}-{:02}-{:02}/", date[0], date[1], date[2])
-    };
+    let pattern = std::env::var("URL_PATTERN").unwrap_or_else(|_| DEFAULT_URL_PATTERN.to_string());
+    let template =
+        std::env::var("S3_PREFIX_TEMPLATE").unwrap_or_else(|_| DEFAULT_PREFIX_TEMPLATE.to_string());
+
+    let re = Regex::new(&pattern)?;
+    let caps = re.captures(url).ok_or_else(|| {
+        format!(
+            "URL '{}' does not match the expected pattern '{}' (set URL_PATTERN to override)",
+            url, pattern
+        )
+    })?;
+
+    let date = caps
+        .name("date")
+        .ok_or("URL_PATTERN matched but has no 'date' capture group")?
+        .as_str();
+    let date_parts: Vec<&str> = date.split('-').collect();
+    if date_parts.len() != 3 {
+        return Err(format!("'date' capture group '{}' is not in YYYY-MM-DD form", date).into());
+    }
+
+    let variant = caps.name("variant").map(|m| m.as_str()).unwrap_or("");
+    let platform = caps.name("platform").map(|m| m.as_str()).unwrap_or("");
+
+    let prefix = template
+        .replace("{year}", date_parts[0])
+        .replace("{month}", date_parts[1])
+        .replace("{day}", date_parts[2])
+        .replace("{variant}", variant)
+        .replace("{platform}", platform);
+
     Ok(prefix)
 }
 
+/// Build the S3 client config, layering an explicit credentials chain
+/// (env vars, then the shared profile file, then the ECS container
+/// credentials endpoint) with a bounded, adaptive retry policy over
+/// throttling.
+///
+/// This pipeline runs on Fargate (IAM task role via the ECS container
+/// credentials endpoint), locally (shared profile/env vars), and in CI,
+/// so no single provider covers every deployment on its own. Note this
+/// chain does not fall back to generic EC2 IMDS — only the ECS
+/// container-credentials endpoint (`AWS_CONTAINER_CREDENTIALS_RELATIVE_URI`)
+/// is wired up, since that's the only one this pipeline's deployments use.
+///
+/// `S3_MAX_ATTEMPTS` (default 5) and `S3_ENDPOINT_URL` (for MinIO/
+/// localstack) are read from the environment so the same binary can
+/// target S3-compatible stores without a recompile.
 pub async fn get_s3_config() -> aws_sdk_s3::Config {
     let s3_region: String =
         std::env::var("S3_REGION").expect("msg: S3_REGION environment variable not set");
 
-    // Load the default AWS configuration (includes credentials from ~/.aws/credentials)
-    let aws_config = aws_config::defaults(aws_config::BehaviorVersion::latest())
+    let max_attempts: u32 = std::env::var("S3_MAX_ATTEMPTS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(5);
+
+    let credentials_chain = CredentialsProviderChain::first_try(
+        "Environment",
+        EnvironmentVariableCredentialsProvider::new(),
+    )
+    .or_else(
+        "Profile",
+        ProfileFileCredentialsProvider::builder().build(),
+    )
+    .or_else(
+        "EcsContainerCredentials",
+        EcsCredentialsProvider::builder().build(),
+    );
+
+    let retry_config = RetryConfig::adaptive().with_max_attempts(max_attempts);
+
+    let mut config_loader = aws_config::defaults(aws_config::BehaviorVersion::latest())
         .region(Region::new(s3_region))
-        .load()
-        .await;
+        .credentials_provider(credentials_chain)
+        .retry_config(retry_config);
+
+    if let Ok(endpoint_url) = std::env::var("S3_ENDPOINT_URL") {
+        config_loader = config_loader.endpoint_url(endpoint_url);
+    }
+
+    let aws_config = config_loader.load().await;
 
     aws_sdk_s3::Config::from(&aws_config)
 }
@@ -36,36 +125,49 @@ pub fn get_file_size(file: &NamedTempFile) -> Result<u64, Box<dyn std::error::Er
     Ok(metadata.len())
 }
 
-// Regex/String utils
-
-fn get_date_from_url(url: &str) -> Result<[u16; 3], Box<dyn std::error::Error>> {
-    // Regex extracts after 'global-'
-    let re = Regex::new(r"global-(\d{4}-\d{2}-\d{2})")?;
-
-    if let Some(caps) = re.captures(url) {
-        let date_str = caps.get(1).ok_or("Date not found in URL")?.as_str();
-        let parts: Vec<&str> = date_str.split('-').collect();
-
-        if parts.len() == 3 {
-            let year: u16 = parts[0].parse()?;
-            let month: u16 = parts[1].parse()?;
-            let day: u16 = parts[2].parse()?;
-            return Ok([year, month, day]);
-        } else {
-            return Err("Invalid date format in URL".into());
-        }
-    } else {
-        return Err("No date found in URL".into());
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    // `get_s3_prefix` reads process-global env vars; serialize the tests
+    // that touch them so they don't race across threads.
+    static URL_PATTERN_ENV_LOCK: Mutex<()> = Mutex::new(());
+
+    #[test]
+    fn get_s3_prefix_uses_default_pattern_and_template() {
+        let _guard = URL_PATTERN_ENV_LOCK.lock().unwrap();
+        std::env::remove_var("URL_PATTERN");
+        std::env::remove_var("S3_PREFIX_TEMPLATE");
+
+        let prefix = get_s3_prefix("https://example.com/global-2026-07-31-full.zip").unwrap();
+
+        assert_eq!(prefix, "global-full/2026-07-31/");
     }
-}
 
-fn is_light_variant(url: &str) -> Result<bool, Box<dyn std::error::Error>> {
-    // Check if the URL contains 'light' in the filename
-    let re = Regex::new(r"global-\d{4}-\d{2}-\d{2}-(light|full)\.zip")?;
+    #[test]
+    fn get_s3_prefix_rejects_url_not_matching_pattern() {
+        let _guard = URL_PATTERN_ENV_LOCK.lock().unwrap();
+        std::env::remove_var("URL_PATTERN");
+        std::env::remove_var("S3_PREFIX_TEMPLATE");
+
+        let result = get_s3_prefix("https://example.com/totally-different-name.zip");
 
-    if re.is_match(url) {
-        Ok(url.contains("-light"))
-    } else {
-        Err("Invalid URL format".into())
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn get_s3_prefix_honors_overridden_pattern_and_template() {
+        let _guard = URL_PATTERN_ENV_LOCK.lock().unwrap();
+        std::env::set_var("URL_PATTERN", r"(?P<platform>[a-z]+)-export-(?P<date>\d{4}-\d{2}-\d{2})\.zip");
+        std::env::set_var("S3_PREFIX_TEMPLATE", "{platform}/{year}/{month}/{day}/");
+
+        let result = get_s3_prefix("https://example.com/tiktok-export-2026-07-31.zip");
+
+        std::env::remove_var("URL_PATTERN");
+        std::env::remove_var("S3_PREFIX_TEMPLATE");
+
+        assert_eq!(result.unwrap(), "tiktok/2026/07/31/");
     }
 }
+